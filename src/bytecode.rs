@@ -0,0 +1,223 @@
+// Compact binary encoding of a `Program`, used for the `.mxb` distribution
+// format. One opcode byte per `Command`; `Push(Const)` additionally carries
+// a little-endian `Data` immediate. `Push(Label)` is resolved to its line
+// index before encoding (the same substitution `generate_fuxxor` already
+// does), so the format never needs to carry label names.
+//
+// Layout: [u32 LE instruction count][instruction]*
+// instruction := opcode byte, then a 4-byte LE `Data` immediate iff opcode
+// is `OP_PUSH`.
+
+use crate::{Command, Data, Program, PushArgument};
+use std::collections::HashMap;
+
+const OP_LEFT: u8 = 0x00;
+const OP_RIGHT: u8 = 0x01;
+const OP_PUSHT: u8 = 0x02;
+const OP_PUSH: u8 = 0x03;
+const OP_POP: u8 = 0x04;
+const OP_DUP: u8 = 0x05;
+const OP_DEL: u8 = 0x06;
+const OP_EQ: u8 = 0x07;
+const OP_NOT: u8 = 0x08;
+const OP_GT: u8 = 0x09;
+const OP_LT: u8 = 0x0a;
+const OP_ADD: u8 = 0x0b;
+const OP_SUB: u8 = 0x0c;
+const OP_MULT: u8 = 0x0d;
+const OP_DIV: u8 = 0x0e;
+const OP_MOD: u8 = 0x0f;
+const OP_READ: u8 = 0x10;
+const OP_PRINT: u8 = 0x11;
+const OP_JMP: u8 = 0x12;
+const OP_JMPC: u8 = 0x13;
+const OP_NULL: u8 = 0x14;
+
+#[derive(Debug, PartialEq)]
+pub enum DisasmError {
+    InvalidOpcode(u8),
+    TruncatedOperand,
+    OffsetOutOfRange,
+}
+
+impl std::fmt::Display for DisasmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisasmError::InvalidOpcode(b) => write!(f, "invalid opcode byte: 0x{:02x}", b),
+            DisasmError::TruncatedOperand => write!(f, "operand cut off before its final byte"),
+            DisasmError::OffsetOutOfRange => {
+                write!(f, "declared instruction count does not match buffer length")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CompileError {
+    UndefinedLabel(String),
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::UndefinedLabel(label) => write!(f, "undefined label: {}", label),
+        }
+    }
+}
+
+/// Lowers `program` into a flat byte image. Labels are resolved to their
+/// line index first, mirroring the label substitution in `generate_fuxxor`.
+pub fn compile(program: &Program) -> Result<Vec<u8>, CompileError> {
+    let mut lines = program.lines.clone();
+    for command in lines.iter_mut() {
+        if let Command::Push(PushArgument::Label(label)) = command {
+            let target = *program
+                .labels
+                .get(label)
+                .ok_or_else(|| CompileError::UndefinedLabel(label.clone()))?;
+            *command = Command::Push(PushArgument::Const(target as Data));
+        }
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(lines.len() as u32).to_le_bytes());
+    for command in &lines {
+        match command {
+            Command::Left => bytes.push(OP_LEFT),
+            Command::Right => bytes.push(OP_RIGHT),
+            Command::PushT => bytes.push(OP_PUSHT),
+            Command::Push(PushArgument::Const(c)) => {
+                bytes.push(OP_PUSH);
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+            Command::Push(PushArgument::Label(_)) => unreachable!("labels resolved above"),
+            Command::Pop => bytes.push(OP_POP),
+            Command::Dup => bytes.push(OP_DUP),
+            Command::Del => bytes.push(OP_DEL),
+            Command::Eq => bytes.push(OP_EQ),
+            Command::Not => bytes.push(OP_NOT),
+            Command::Gt => bytes.push(OP_GT),
+            Command::Lt => bytes.push(OP_LT),
+            Command::Add => bytes.push(OP_ADD),
+            Command::Sub => bytes.push(OP_SUB),
+            Command::Mult => bytes.push(OP_MULT),
+            Command::Div => bytes.push(OP_DIV),
+            Command::Mod => bytes.push(OP_MOD),
+            Command::Read => bytes.push(OP_READ),
+            Command::Print => bytes.push(OP_PRINT),
+            Command::Jmp => bytes.push(OP_JMP),
+            Command::JmpC => bytes.push(OP_JMPC),
+            Command::Null | Command::Label(_) => bytes.push(OP_NULL),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Inverse of [`compile`]. Labels are not reconstructed (the compiled form
+/// no longer carries their names), so the resulting `Program` has an empty
+/// `labels` map; jump targets remain valid as they were already resolved
+/// to line indices at compile time.
+pub fn disasm(bytes: &[u8]) -> Result<Program, DisasmError> {
+    if bytes.len() < 4 {
+        return Err(DisasmError::OffsetOutOfRange);
+    }
+    let count = u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize;
+    let mut cursor = 4;
+    let mut lines = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let opcode = *bytes.get(cursor).ok_or(DisasmError::OffsetOutOfRange)?;
+        cursor += 1;
+        let command = match opcode {
+            OP_LEFT => Command::Left,
+            OP_RIGHT => Command::Right,
+            OP_PUSHT => Command::PushT,
+            OP_PUSH => {
+                let operand = bytes
+                    .get(cursor..cursor + 4)
+                    .ok_or(DisasmError::TruncatedOperand)?;
+                cursor += 4;
+                Command::Push(PushArgument::Const(Data::from_le_bytes(
+                    operand.try_into().unwrap(),
+                )))
+            }
+            OP_POP => Command::Pop,
+            OP_DUP => Command::Dup,
+            OP_DEL => Command::Del,
+            OP_EQ => Command::Eq,
+            OP_NOT => Command::Not,
+            OP_GT => Command::Gt,
+            OP_LT => Command::Lt,
+            OP_ADD => Command::Add,
+            OP_SUB => Command::Sub,
+            OP_MULT => Command::Mult,
+            OP_DIV => Command::Div,
+            OP_MOD => Command::Mod,
+            OP_READ => Command::Read,
+            OP_PRINT => Command::Print,
+            OP_JMP => Command::Jmp,
+            OP_JMPC => Command::JmpC,
+            OP_NULL => Command::Null,
+            other => return Err(DisasmError::InvalidOpcode(other)),
+        };
+        lines.push(command);
+    }
+
+    if cursor != bytes.len() {
+        return Err(DisasmError::OffsetOutOfRange);
+    }
+
+    Ok(Program {
+        lines,
+        labels: HashMap::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+
+    #[test]
+    fn round_trips_a_simple_program() {
+        let program = parse_program("push 3\npush 4\nadd\nprint\n");
+        let bytes = compile(&program).unwrap();
+        let decoded = disasm(&bytes).unwrap();
+        assert_eq!(decoded.lines, program.lines);
+    }
+
+    #[test]
+    fn resolves_labels_to_line_indices() {
+        let program = parse_program("start:\npush start\njmp\n");
+        let bytes = compile(&program).unwrap();
+        let decoded = disasm(&bytes).unwrap();
+        assert_eq!(decoded.lines[1], Command::Push(PushArgument::Const(0)));
+    }
+
+    #[test]
+    fn rejects_undefined_label() {
+        let program = parse_program("push nonexistent\njmp\n");
+        assert_eq!(
+            compile(&program).unwrap_err(),
+            CompileError::UndefinedLabel("nonexistent".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_opcode() {
+        let bytes = vec![1, 0, 0, 0, 0xff];
+        assert_eq!(disasm(&bytes).unwrap_err(), DisasmError::InvalidOpcode(0xff));
+    }
+
+    #[test]
+    fn rejects_truncated_push_operand() {
+        let bytes = vec![1, 0, 0, 0, OP_PUSH, 1, 2];
+        assert_eq!(disasm(&bytes).unwrap_err(), DisasmError::TruncatedOperand);
+    }
+
+    #[test]
+    fn rejects_count_mismatch() {
+        let bytes = vec![2, 0, 0, 0, OP_NULL];
+        assert_eq!(disasm(&bytes).unwrap_err(), DisasmError::OffsetOutOfRange);
+    }
+}
@@ -0,0 +1,336 @@
+// Hand-rolled subcommand parsing, shaped the way the `xflags` crate would
+// generate it (a struct per subcommand, a top-level `Cmd` enum, a
+// `parse_or_exit` entry point and a generated `HELP` string) but written by
+// hand since we don't depend on the macro.
+//
+// The `while let Some(arg) = iter.next()` loops below pull extra values off
+// `iter` for flags that take an argument, so they can't be plain `for` loops.
+#![allow(clippy::while_let_on_iterator)]
+
+use std::path::PathBuf;
+
+pub const HELP: &str = "\
+mexirun
+
+USAGE:
+  mexirun run <file> [--limit N] [--unicode] [-v]
+  mexirun obfuscate <file> [--out PATH] [--passes N] [--seed N] [-v]
+  mexirun compile <file> [--out PATH]
+  mexirun disasm <file> [-v]
+  mexirun debug <file> [--break LINE|LABEL]... [--unicode]
+
+SUBCOMMANDS:
+  run        execute a program
+  obfuscate  write an obfuscated copy of a program without executing it
+  compile    lower a program to the compact .mxb bytecode format
+  disasm     pretty-print a program (source or .mxb bytecode)
+  debug      step through a program interactively, with breakpoints
+
+OPTIONS:
+  --out PATH     output path (obfuscate only, default: fuxxor.mxc)
+  --passes N     number of churn rounds before resolving labels (obfuscate only, default: 1)
+  --seed N       RNG seed for reproducible obfuscation (obfuscate only, default: random)
+  --limit N      maximum number of executed instructions before bailing out (run only)
+  --break T      add a breakpoint at line T or label T; may repeat (debug only)
+  --unicode      read/print full Unicode codepoints instead of single bytes (run, debug)
+  -v, --verbose  print extra diagnostics
+";
+
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub file: PathBuf,
+    pub limit: Option<usize>,
+    pub unicode: bool,
+    pub verbose: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Obfuscate {
+    pub file: PathBuf,
+    pub out: PathBuf,
+    pub passes: usize,
+    pub seed: Option<u64>,
+    pub verbose: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Compile {
+    pub file: PathBuf,
+    pub out: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Disasm {
+    pub file: PathBuf,
+    pub verbose: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Debug {
+    pub file: PathBuf,
+    pub breakpoints: Vec<String>,
+    pub unicode: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Cmd {
+    Run(Run),
+    Obfuscate(Obfuscate),
+    Compile(Compile),
+    Disasm(Disasm),
+    Debug(Debug),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    MissingSubcommand,
+    UnknownSubcommand(String),
+    MissingFile,
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::MissingSubcommand => write!(f, "no subcommand given"),
+            CliError::UnknownSubcommand(s) => write!(f, "unknown subcommand: {}", s),
+            CliError::MissingFile => write!(f, "missing required <file> argument"),
+            CliError::MissingValue(flag) => write!(f, "{} expects a value", flag),
+            CliError::InvalidValue(flag, v) => write!(f, "invalid value for {}: {}", flag, v),
+        }
+    }
+}
+
+/// Parses `args` (excluding the `argv[0]` binary name) into a `Cmd`.
+pub fn parse(args: &[String]) -> Result<Cmd, CliError> {
+    let (sub, rest) = args.split_first().ok_or(CliError::MissingSubcommand)?;
+    match sub.as_str() {
+        "run" => parse_run(rest).map(Cmd::Run),
+        "obfuscate" => parse_obfuscate(rest).map(Cmd::Obfuscate),
+        "compile" => parse_compile(rest).map(Cmd::Compile),
+        "disasm" => parse_disasm(rest).map(Cmd::Disasm),
+        "debug" => parse_debug(rest).map(Cmd::Debug),
+        other => Err(CliError::UnknownSubcommand(other.to_string())),
+    }
+}
+
+fn parse_run(args: &[String]) -> Result<Run, CliError> {
+    let mut file = None;
+    let mut limit = None;
+    let mut unicode = false;
+    let mut verbose = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--limit" => {
+                let raw = iter.next().ok_or(CliError::MissingValue("--limit"))?;
+                limit = Some(
+                    raw.parse::<usize>()
+                        .map_err(|_| CliError::InvalidValue("--limit", raw.clone()))?,
+                );
+            }
+            "--unicode" => unicode = true,
+            "-v" | "--verbose" => verbose = true,
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            other => return Err(CliError::InvalidValue("<file>", other.to_string())),
+        }
+    }
+    Ok(Run {
+        file: file.ok_or(CliError::MissingFile)?,
+        limit,
+        unicode,
+        verbose,
+    })
+}
+
+fn parse_obfuscate(args: &[String]) -> Result<Obfuscate, CliError> {
+    let mut file = None;
+    let mut out = None;
+    let mut passes = 1;
+    let mut seed = None;
+    let mut verbose = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--out" => {
+                let raw = iter.next().ok_or(CliError::MissingValue("--out"))?;
+                out = Some(PathBuf::from(raw));
+            }
+            "--passes" => {
+                let raw = iter.next().ok_or(CliError::MissingValue("--passes"))?;
+                passes = raw
+                    .parse::<usize>()
+                    .map_err(|_| CliError::InvalidValue("--passes", raw.clone()))?;
+            }
+            "--seed" => {
+                let raw = iter.next().ok_or(CliError::MissingValue("--seed"))?;
+                seed = Some(
+                    raw.parse::<u64>()
+                        .map_err(|_| CliError::InvalidValue("--seed", raw.clone()))?,
+                );
+            }
+            "-v" | "--verbose" => verbose = true,
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            other => return Err(CliError::InvalidValue("<file>", other.to_string())),
+        }
+    }
+    Ok(Obfuscate {
+        file: file.ok_or(CliError::MissingFile)?,
+        out: out.unwrap_or_else(|| PathBuf::from("fuxxor.mxc")),
+        passes,
+        seed,
+        verbose,
+    })
+}
+
+fn parse_compile(args: &[String]) -> Result<Compile, CliError> {
+    let mut file = None;
+    let mut out = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--out" => {
+                let raw = iter.next().ok_or(CliError::MissingValue("--out"))?;
+                out = Some(PathBuf::from(raw));
+            }
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            other => return Err(CliError::InvalidValue("<file>", other.to_string())),
+        }
+    }
+    Ok(Compile {
+        file: file.ok_or(CliError::MissingFile)?,
+        out: out.unwrap_or_else(|| PathBuf::from("out.mxb")),
+    })
+}
+
+fn parse_disasm(args: &[String]) -> Result<Disasm, CliError> {
+    let mut file = None;
+    let mut verbose = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-v" | "--verbose" => verbose = true,
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            other => return Err(CliError::InvalidValue("<file>", other.to_string())),
+        }
+    }
+    Ok(Disasm {
+        file: file.ok_or(CliError::MissingFile)?,
+        verbose,
+    })
+}
+
+fn parse_debug(args: &[String]) -> Result<Debug, CliError> {
+    let mut file = None;
+    let mut breakpoints = Vec::new();
+    let mut unicode = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--break" => {
+                let raw = iter.next().ok_or(CliError::MissingValue("--break"))?;
+                breakpoints.push(raw.clone());
+            }
+            "--unicode" => unicode = true,
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            other => return Err(CliError::InvalidValue("<file>", other.to_string())),
+        }
+    }
+    Ok(Debug {
+        file: file.ok_or(CliError::MissingFile)?,
+        breakpoints,
+        unicode,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_run() {
+        let cmd = parse(&args(&["run", "prog.mx", "--limit", "10", "--unicode", "-v"])).unwrap();
+        assert_eq!(
+            cmd,
+            Cmd::Run(Run {
+                file: PathBuf::from("prog.mx"),
+                limit: Some(10),
+                unicode: true,
+                verbose: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_obfuscate_defaults() {
+        let cmd = parse(&args(&["obfuscate", "prog.mx"])).unwrap();
+        assert_eq!(
+            cmd,
+            Cmd::Obfuscate(Obfuscate {
+                file: PathBuf::from("prog.mx"),
+                out: PathBuf::from("fuxxor.mxc"),
+                passes: 1,
+                seed: None,
+                verbose: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_compile_defaults() {
+        let cmd = parse(&args(&["compile", "prog.mx"])).unwrap();
+        assert_eq!(
+            cmd,
+            Cmd::Compile(Compile {
+                file: PathBuf::from("prog.mx"),
+                out: PathBuf::from("out.mxb"),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_disasm() {
+        let cmd = parse(&args(&["disasm", "prog.mxc"])).unwrap();
+        assert_eq!(
+            cmd,
+            Cmd::Disasm(Disasm {
+                file: PathBuf::from("prog.mxc"),
+                verbose: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_debug_breakpoints() {
+        let cmd = parse(&args(&[
+            "debug", "prog.mx", "--break", "4", "--break", "loop", "--unicode",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cmd,
+            Cmd::Debug(Debug {
+                file: PathBuf::from("prog.mx"),
+                breakpoints: vec!["4".to_string(), "loop".to_string()],
+                unicode: true,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_missing_subcommand() {
+        assert_eq!(parse(&args(&[])), Err(CliError::MissingSubcommand));
+    }
+
+    #[test]
+    fn rejects_unknown_subcommand() {
+        assert_eq!(
+            parse(&args(&["frobnicate", "x"])),
+            Err(CliError::UnknownSubcommand("frobnicate".to_string()))
+        );
+    }
+}
@@ -0,0 +1,241 @@
+// A stepping front-end for `execute_command`. Where `execute_or_crash`
+// panics on any `ExecutionError`, `Debugger` surfaces the same errors as a
+// `Fault` — a snapshot of exactly where and why execution stopped — so a
+// caller (e.g. a REPL) can inspect or patch `machine` and keep going.
+
+use crate::{execute_command, Command, ExecutionError, MachineState, Program};
+
+/// Where a breakpoint should trigger execution to stop.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    Line(usize),
+    Label(String),
+}
+
+/// A snapshot of the machine at the instruction that failed to execute.
+/// Recoverable rather than fatal: `machine` is left exactly as it was
+/// before the failed instruction, so the caller can patch it (through
+/// `Debugger::machine_mut`) and call `resume` to retry.
+#[derive(Debug)]
+pub struct Fault {
+    pub error: ExecutionError,
+    pub program_counter: usize,
+    pub command: Command,
+    pub stack_depth: usize,
+    pub tape_head: i64,
+}
+
+/// Why a `run`/`resume` call returned control to the caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Breakpoint,
+    Terminated,
+    StepBudgetExhausted,
+}
+
+/// Drives a `Program` one instruction at a time, or until a breakpoint, a
+/// step budget, or a fault stops it.
+pub struct Debugger<'p> {
+    program: &'p Program,
+    machine: MachineState,
+    breakpoints: Vec<Breakpoint>,
+    /// Set when the previous `run`/`resume` call stopped on a breakpoint,
+    /// so the next call knows to force one step past it instead of
+    /// re-checking the same program counter and getting stuck forever.
+    resuming_from_breakpoint: bool,
+}
+
+impl<'p> Debugger<'p> {
+    pub fn new(program: &'p Program, machine: MachineState) -> Self {
+        Debugger {
+            program,
+            machine,
+            breakpoints: Vec::new(),
+            resuming_from_breakpoint: false,
+        }
+    }
+
+    pub fn machine(&self) -> &MachineState {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut MachineState {
+        &mut self.machine
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        self.breakpoints.iter().any(|breakpoint| match breakpoint {
+            Breakpoint::Line(line) => *line == self.machine.program_counter,
+            Breakpoint::Label(label) => {
+                self.program.labels.get(label) == Some(&self.machine.program_counter)
+            }
+        })
+    }
+
+    /// Executes the instruction at the current program counter. A no-op if
+    /// the program has already terminated. On error, `machine` is left
+    /// untouched and the failure is returned as a `Fault` instead of
+    /// propagating a panic.
+    pub fn step(&mut self) -> Result<(), Fault> {
+        if self.machine.terminated {
+            return Ok(());
+        }
+        let program_counter = self.machine.program_counter;
+        match execute_command(self.program, self.machine.clone()) {
+            Ok(next) => {
+                self.machine = next;
+                Ok(())
+            }
+            Err(error) => Err(Fault {
+                error,
+                program_counter,
+                command: self
+                    .program
+                    .lines
+                    .get(program_counter)
+                    .cloned()
+                    .unwrap_or(Command::Null),
+                stack_depth: self.machine.stack.len(),
+                tape_head: self.machine.tape.head(),
+            }),
+        }
+    }
+
+    /// Steps until termination, a breakpoint, a fault, or `step_budget`
+    /// instructions have run, whichever comes first (`None` is unbounded).
+    /// A breakpoint at the current program counter stops execution before
+    /// any instruction runs, so a fresh `Debugger` can stop right at entry.
+    /// The one exception is the instruction right after a previous
+    /// breakpoint stop: that one is always executed first, so calling
+    /// `run` again right after stopping on a breakpoint makes progress
+    /// rather than stopping on the same line forever.
+    pub fn run(&mut self, step_budget: Option<usize>) -> Result<StopReason, Fault> {
+        let mut steps = 0;
+        let mut skip_breakpoint_check = self.resuming_from_breakpoint;
+        self.resuming_from_breakpoint = false;
+        loop {
+            if self.machine.terminated {
+                return Ok(StopReason::Terminated);
+            }
+            if !skip_breakpoint_check && self.at_breakpoint() {
+                self.resuming_from_breakpoint = true;
+                return Ok(StopReason::Breakpoint);
+            }
+            skip_breakpoint_check = false;
+            if step_budget.is_some_and(|budget| steps >= budget) {
+                return Ok(StopReason::StepBudgetExhausted);
+            }
+            self.step()?;
+            steps += 1;
+        }
+    }
+
+    /// Continues execution after a `Fault`, once the caller has patched
+    /// `machine` (via `machine_mut`) to make the retry succeed.
+    pub fn resume(&mut self, step_budget: Option<usize>) -> Result<StopReason, Fault> {
+        self.run(step_budget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+
+    #[test]
+    fn steps_one_instruction_at_a_time() {
+        let program = parse_program("push 3\npush 4\nadd\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        debugger.step().unwrap();
+        assert_eq!(debugger.machine().stack, vec![3]);
+        debugger.step().unwrap();
+        assert_eq!(debugger.machine().stack, vec![3, 4]);
+        debugger.step().unwrap();
+        assert_eq!(debugger.machine().stack, vec![7]);
+    }
+
+    #[test]
+    fn runs_to_completion() {
+        let program = parse_program("push 3\npush 4\nadd\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        let reason = debugger.run(None).unwrap();
+        assert_eq!(reason, StopReason::Terminated);
+        assert_eq!(debugger.machine().stack, vec![7]);
+    }
+
+    #[test]
+    fn stops_at_a_line_breakpoint() {
+        let program = parse_program("push 3\npush 4\nadd\nprint\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        debugger.add_breakpoint(Breakpoint::Line(2));
+        let reason = debugger.run(None).unwrap();
+        assert_eq!(reason, StopReason::Breakpoint);
+        assert_eq!(debugger.machine().program_counter, 2);
+        assert_eq!(debugger.machine().stack, vec![3, 4]);
+    }
+
+    #[test]
+    fn stops_at_a_breakpoint_on_the_entry_line() {
+        let program = parse_program("push 3\npush 4\nadd\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        debugger.add_breakpoint(Breakpoint::Line(0));
+        let reason = debugger.run(None).unwrap();
+        assert_eq!(reason, StopReason::Breakpoint);
+        assert_eq!(debugger.machine().program_counter, 0);
+        assert!(debugger.machine().stack.is_empty());
+    }
+
+    #[test]
+    fn resuming_past_a_breakpoint_makes_progress() {
+        let program = parse_program("push 3\npush 4\nadd\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        debugger.add_breakpoint(Breakpoint::Line(0));
+        assert_eq!(debugger.run(None).unwrap(), StopReason::Breakpoint);
+        assert_eq!(debugger.resume(None).unwrap(), StopReason::Terminated);
+        assert_eq!(debugger.machine().stack, vec![7]);
+    }
+
+    #[test]
+    fn stops_at_a_label_breakpoint() {
+        let program = parse_program("push 0\nstart:\npush 1\nadd\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        debugger.add_breakpoint(Breakpoint::Label("start".to_string()));
+        let reason = debugger.run(None).unwrap();
+        assert_eq!(reason, StopReason::Breakpoint);
+        assert_eq!(debugger.machine().program_counter, 1);
+    }
+
+    #[test]
+    fn honors_a_step_budget() {
+        let program = parse_program("push 1\npush 2\npush 3\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        let reason = debugger.run(Some(2)).unwrap();
+        assert_eq!(reason, StopReason::StepBudgetExhausted);
+        assert_eq!(debugger.machine().stack, vec![1, 2]);
+    }
+
+    #[test]
+    fn surfaces_a_fault_instead_of_panicking() {
+        let program = parse_program("add\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        let fault = debugger.step().unwrap_err();
+        assert!(matches!(fault.error, ExecutionError::StackUnderflow));
+        assert_eq!(fault.program_counter, 0);
+        assert_eq!(fault.command, Command::Add);
+        assert_eq!(fault.stack_depth, 0);
+    }
+
+    #[test]
+    fn resumes_after_a_fault_once_patched() {
+        let program = parse_program("add\nprint\n");
+        let mut debugger = Debugger::new(&program, MachineState::default());
+        debugger.step().unwrap_err();
+        debugger.machine_mut().stack.extend([3, 4]);
+        let reason = debugger.resume(None).unwrap();
+        assert_eq!(reason, StopReason::Terminated);
+    }
+}
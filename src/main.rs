@@ -1,9 +1,17 @@
-use std::{collections::HashMap, io::{Write, Read}, fs::{self, File}, env};
+use std::{collections::HashMap, io::{BufRead, Write, Read}, fs, env};
 use rand::Rng;
 
+mod bytecode;
+mod cli;
+mod debugger;
+mod obfuscate;
+mod tape;
+use cli::Cmd;
+use tape::Tape;
+
 type Data = i32;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 struct Program {
     lines: Vec<Command>,
     labels: HashMap<String, usize>,
@@ -72,13 +80,23 @@ impl ToString for Command {
     }
 }
 
+/// Whether `Read`/`Print` treat a `Data` value as a raw byte (0-255) or as a
+/// full Unicode scalar value. Byte mode is the default so existing programs
+/// keep their original behavior; codepoint mode is opt-in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum IoMode {
+    #[default]
+    Byte,
+    Codepoint,
+}
+
 #[derive(Default, Debug, Clone)]
 struct MachineState {
     program_counter: usize,
-    tape_head: usize,
-    tape: Vec<Data>,
+    tape: Tape,
     stack: Vec<Data>,
     terminated: bool,
+    io_mode: IoMode,
 }
 
 fn is_comment(line: &str) -> bool{
@@ -150,10 +168,38 @@ enum ExecutionError {
     InvalidLine(usize),
     InvalidLabel(String),
     StackUnderflow,
-    TapeHeadUnderflow,
+    InvalidCodepoint(u32),
+    InvalidUtf8,
 }
 use ExecutionError::*;
 
+/// Number of bytes in the UTF-8 sequence that starts with `first_byte`.
+fn utf8_sequence_len(first_byte: u8) -> Result<usize, ExecutionError> {
+    match first_byte {
+        0x00..=0x7f => Ok(1),
+        0xc0..=0xdf => Ok(2),
+        0xe0..=0xef => Ok(3),
+        0xf0..=0xf7 => Ok(4),
+        _ => Err(InvalidUtf8),
+    }
+}
+
+/// Reads one full UTF-8 character from `reader`.
+fn read_utf8_char<R: std::io::Read>(reader: &mut R) -> Result<char, ExecutionError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf[..1]).unwrap();
+    let len = utf8_sequence_len(buf[0])?;
+    if len > 1 {
+        reader
+            .read_exact(&mut buf[1..len])
+            .map_err(|_| InvalidUtf8)?;
+    }
+    std::str::from_utf8(&buf[..len])
+        .ok()
+        .and_then(|s| s.chars().next())
+        .ok_or(InvalidUtf8)
+}
+
 fn execute_command(
     program: &Program,
     mut machine: MachineState,
@@ -172,22 +218,16 @@ fn execute_command(
         .clone();
     machine.program_counter += 1;
     match command {
-        Command::Left => {
-            machine.tape_head = machine.tape_head.checked_sub(1).ok_or(TapeHeadUnderflow)?
-        }
-        Command::Right => machine.tape_head += 1,
-        Command::PushT => machine.stack.push(machine.tape[machine.tape_head]),
+        Command::Left => machine.tape.left(),
+        Command::Right => machine.tape.right(),
+        Command::PushT => machine.stack.push(machine.tape.read()),
         Command::Push(PushArgument::Const(c)) => machine.stack.push(c),
         Command::Push(PushArgument::Label(label)) => machine
             .stack
             .push(*program.labels.get(&label).ok_or(InvalidLabel(label))? as Data),
         Command::Pop => {
-            if machine.tape_head >= machine.tape.len() {
-                machine
-                    .tape
-                    .resize_with(machine.tape_head + 1, Data::default)
-            };
-            machine.tape[machine.tape_head] = machine.stack.pop().ok_or(StackUnderflow)?
+            let value = machine.stack.pop().ok_or(StackUnderflow)?;
+            machine.tape.write(value)
         }
         Command::Dup => machine
             .stack
@@ -237,12 +277,23 @@ fn execute_command(
                 % machine.stack.pop().ok_or(StackUnderflow)?;
             machine.stack.push(result as Data)
         }
-        Command::Read => machine.stack.push(std::io::stdin().lock().bytes().next().unwrap().unwrap() as Data),
+        Command::Read => {
+            let value = match machine.io_mode {
+                IoMode::Byte => std::io::stdin().lock().bytes().next().unwrap().unwrap() as Data,
+                IoMode::Codepoint => read_utf8_char(&mut std::io::stdin().lock())? as Data,
+            };
+            machine.stack.push(value)
+        }
         Command::Print => {
-            print!(
-                "{}",
-                machine.stack.pop().ok_or(StackUnderflow)? as u8 as char
-            );
+            let value = machine.stack.pop().ok_or(StackUnderflow)?;
+            match machine.io_mode {
+                IoMode::Byte => print!("{}", value as u8 as char),
+                IoMode::Codepoint => {
+                    let codepoint = value as u32;
+                    let c = char::from_u32(codepoint).ok_or(InvalidCodepoint(codepoint))?;
+                    print!("{}", c);
+                }
+            }
             std::io::stdout().flush().unwrap();
         }
         Command::Jmp => {
@@ -262,85 +313,10 @@ fn execute_command(
     Ok(machine)
 }
 
-// Anything passed to this function needs to be effectively a noop. 
-fn insert_dead_code<T: FnMut() -> Vec<Command>>(mut program: Program, mut dead_code_gen: T, quantity: usize) -> Program {
-    let mut rng = rand::thread_rng();
-    for _ in 0..quantity {
-        let mut index = rng.gen_range(0..program.lines.len());
-        let dead_code = dead_code_gen();
-        for command in dead_code.iter() {
-            program.lines.insert(index, command.clone());
-            index += 1;
-        }
-    }
-    program
-}
-
-// This code is ugly. Do not read. It just generates an obfuscated script called ./fuxxor.mxc
-// Not even sure if it still does the same thing after my refactor ...
-// The original code also tried to add nonsense labels that it then removed a few lines later ...
-// ... in my defense, I was tired ...
-fn generate_fuxxor(mut program: Program){
-    program.lines.retain(|command| *command != Command::Null);
-
-    for _ in 0..100 {
-        let mut rng = rand::thread_rng();
-        for _ in 0..rng.gen_range(1..4){
-            program.lines.insert(rng.gen_range(0..program.lines.len()), Command::Null);
-        }
-    }
-
-
-    let mut rng = rand::thread_rng();
-
-    program = insert_dead_code(program, || vec![Command::Push(PushArgument::Const(rng.gen_range(-3..10))), Command::Del], 200);
-    program = insert_dead_code(program, || vec![Command::Push(PushArgument::Const(rng.gen_range(-3..10))), Command::Push(PushArgument::Const(rng.gen_range(-3..10))), Command::Add, Command::Del], 200);
-    program = insert_dead_code(program, || vec![Command::Push(PushArgument::Const(rng.gen_range(-3..10))), Command::Push(PushArgument::Const(rng.gen_range(-3..10))), Command::Sub, Command::Del], 200);
-    program = insert_dead_code(program, || vec![Command::Push(PushArgument::Const(rng.gen_range(-3..10))), Command::Dup, Command::Add, Command::Del], 200);
-    program = insert_dead_code(program, || vec![Command::Push(PushArgument::Const(rng.gen_range(3..10))), Command::Push(PushArgument::Const(rng.gen_range(3..10))), Command::Div, Command::Push(PushArgument::Const(rng.gen_range(3..10))), Command::Mult,  Command::Del], 20);
-    program = insert_dead_code(program, || vec![Command::Push(PushArgument::Const(rng.gen_range(-3..10))), Command::Dup, Command::Mult, Command::Push(PushArgument::Const(rng.gen_range(3..10))), Command::Mult,  Command::Del], 20);
-    program = insert_dead_code(program, || vec![Command::Null], 100);
-
-    // update label locations
-    program.labels = HashMap::new();
+fn pretty_print_program(program: &Program) {
     for (line, command) in program.lines.iter().enumerate() {
-        if let Command::Label(label) = command{
-            program.labels.insert(label.clone(), line);
-        }
-    }
-
-    // substitute labels for consts
-    for  command in program.lines.iter_mut() {
-        if let Command::Push(PushArgument::Label(label)) = command{
-            *command = Command::Push(PushArgument::Const(*program.labels.get(label).unwrap() as i32));
-        }
+        println!("{:>4}: {}", line, command.to_string());
     }
-    program.lines = program.lines.iter().map(|command| match command.clone() {Command::Label(_) => Command::Null, x => x}).collect();
-
-    let mut rng = rand::thread_rng();
-    let mut indent: String = "".into();
-    let prog_string: String = program.lines.iter().map(|command| {
-        let mut line = format!("{}{}", indent, command.to_string());
-        // add trailing space
-        if rng.gen_ratio(1, 50){
-            line.push(' ');
-        }
-        // generate random indentation
-        if rng.gen_ratio(1, 20){
-            indent.push(' ');
-        }
-        if rng.gen_ratio(1, 20){
-            indent.push('\t');
-        }
-        if rng.gen_ratio(1, 8){
-            indent.pop();
-        }
-        line.push('\n');
-        line
-    }).collect();
-    let mut file = File::create("fuxxor.mxc").unwrap();
-    let bytes: Vec<u8> = prog_string.bytes().collect();
-    file.write_all(&bytes).unwrap();
 }
 
 #[cfg(debug_assertions)]
@@ -348,9 +324,9 @@ fn execute_or_crash(program: &Program, machine: MachineState) -> MachineState {
     match execute_command(&program, machine.clone()) { // this clone slows everything down
         Ok(new_state) => new_state,
         Err(e) => {
-            println!("\n\nProgram crashed, error: {:?}\n\nState before failed execution: \nProgram counter: {}\nTape head: {}\nTape length: {}\nStack size: {} Command: {:?}\n", 
-                e, machine.program_counter, machine.tape_head, machine.tape.len(), &machine.stack.len(), &program.lines[machine.program_counter]);
-            println!("\nProgram crashed with tape state:\n\n{:?}", machine.tape);
+            println!("\n\nProgram crashed, error: {:?}\n\nState before failed execution: \nProgram counter: {}\nTape head: {}\nCells touched: {}\nStack size: {} Command: {:?}\n",
+                e, machine.program_counter, machine.tape.head(), machine.tape.touched(), &machine.stack.len(), &program.lines[machine.program_counter]);
+            println!("\nProgram crashed with tape state:\n\n{:?}", machine.tape.snapshot());
             dbg!(machine.stack);
             panic!()
         }
@@ -366,13 +342,188 @@ fn execute_or_crash(program: &Program, machine: MachineState) -> MachineState {
     }
 }
 
-fn main() {
-    let prog_string = fs::read_to_string(env::args().nth(1).expect("file argument needed")).expect("invalid file");
-    let program = parse_program(&prog_string);
-    generate_fuxxor(program.clone());
-    let mut machine = MachineState::default();
+/// Loads a `Program` from `path`, routing through `bytecode::disasm` for a
+/// `.mxb` image and through `parse_program` for everything else.
+fn load_program(path: &std::path::Path) -> Program {
+    if path.extension().is_some_and(|ext| ext == "mxb") {
+        let bytes = fs::read(path).expect("invalid file");
+        bytecode::disasm(&bytes).unwrap_or_else(|e| panic!("malformed bytecode: {}", e))
+    } else {
+        let prog_string = fs::read_to_string(path).expect("invalid file");
+        parse_program(&prog_string)
+    }
+}
+
+fn run(args: cli::Run) {
+    let program = load_program(&args.file);
+    let mut machine = MachineState {
+        io_mode: if args.unicode { IoMode::Codepoint } else { IoMode::Byte },
+        ..Default::default()
+    };
+    let mut steps = 0usize;
     while !machine.terminated {
+        if let Some(limit) = args.limit {
+            if steps >= limit {
+                println!("\nStep limit of {} reached, aborting.", limit);
+                break;
+            }
+        }
         machine = execute_or_crash(&program, machine);
+        steps += 1;
+    }
+    if args.verbose {
+        println!("\nExecuted {} steps.", steps);
+    }
+    println!("\nProgram terminated with tape state:\n\n{:?}", machine.tape.snapshot())
+}
+
+fn obfuscate(args: cli::Obfuscate) {
+    let prog_string = fs::read_to_string(&args.file).expect("invalid file");
+    let program = parse_program(&prog_string);
+    let seed = args.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    if args.verbose {
+        println!("running {} round(s) of obfuscation with seed {}", args.passes, seed);
+    }
+    let rendered = obfuscate::obfuscate(program, &obfuscate::default_pipeline(args.passes), seed);
+    fs::write(&args.out, &rendered).unwrap();
+    println!("wrote obfuscated program to {}", args.out.display());
+}
+
+fn compile(args: cli::Compile) {
+    let prog_string = fs::read_to_string(&args.file).expect("invalid file");
+    let program = parse_program(&prog_string);
+    let bytes = bytecode::compile(&program)
+        .unwrap_or_else(|e| panic!("cannot compile program: {}", e));
+    fs::write(&args.out, &bytes).unwrap();
+    println!("wrote {} bytes to {}", bytes.len(), args.out.display());
+}
+
+fn disasm(args: cli::Disasm) {
+    let program = load_program(&args.file);
+    if args.verbose {
+        println!("{} lines, {} labels\n", program.lines.len(), program.labels.len());
+    }
+    pretty_print_program(&program);
+}
+
+fn parse_breakpoint(target: &str) -> debugger::Breakpoint {
+    target
+        .parse::<usize>()
+        .map(debugger::Breakpoint::Line)
+        .unwrap_or_else(|_| debugger::Breakpoint::Label(target.to_string()))
+}
+
+fn print_debugger_state(debugger: &debugger::Debugger) {
+    let machine = debugger.machine();
+    println!(
+        "pc: {}  stack: {:?}  tape head: {}  tape: {:?}",
+        machine.program_counter,
+        machine.stack,
+        machine.tape.head(),
+        machine.tape.snapshot()
+    );
+}
+
+fn print_fault(fault: &debugger::Fault) {
+    println!(
+        "\nfault at pc {}: {:?} while executing {:?} (stack depth {}, tape head {})",
+        fault.program_counter, fault.error, fault.command, fault.stack_depth, fault.tape_head
+    );
+    println!("patch state with `push`/`pop` and `resume`, or `quit`.");
+}
+
+fn debug(args: cli::Debug) {
+    let program = load_program(&args.file);
+    let machine = MachineState {
+        io_mode: if args.unicode { IoMode::Codepoint } else { IoMode::Byte },
+        ..Default::default()
+    };
+    let mut dbg = debugger::Debugger::new(&program, machine);
+    for target in &args.breakpoints {
+        dbg.add_breakpoint(parse_breakpoint(target));
+    }
+
+    println!("mexirun debugger. Commands: step, run, break <line|label>, print, push <n>, pop, quit.");
+    print_debugger_state(&dbg);
+
+    let stdin = std::io::stdin();
+    let mut faulted = false;
+    for line in stdin.lock().lines() {
+        let line = line.expect("failed to read from stdin");
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("step" | "s") => {
+                faulted = match dbg.step() {
+                    Ok(()) => {
+                        print_debugger_state(&dbg);
+                        false
+                    }
+                    Err(fault) => {
+                        print_fault(&fault);
+                        true
+                    }
+                };
+            }
+            Some("run" | "r") => {
+                if faulted {
+                    println!("program faulted; `push`/`pop` then `resume` to retry, or `quit`.");
+                    continue;
+                }
+                match dbg.run(None) {
+                    Ok(reason) => {
+                        println!("stopped: {:?}", reason);
+                        print_debugger_state(&dbg);
+                    }
+                    Err(fault) => {
+                        print_fault(&fault);
+                        faulted = true;
+                    }
+                }
+            }
+            Some("resume") => match dbg.resume(None) {
+                Ok(reason) => {
+                    println!("stopped: {:?}", reason);
+                    print_debugger_state(&dbg);
+                    faulted = false;
+                }
+                Err(fault) => print_fault(&fault),
+            },
+            Some("break" | "b") => {
+                let Some(target) = words.next() else {
+                    println!("usage: break <line|label>");
+                    continue;
+                };
+                dbg.add_breakpoint(parse_breakpoint(target));
+            }
+            Some("print" | "p") => print_debugger_state(&dbg),
+            Some("push") => match words.next().and_then(|n| n.parse::<Data>().ok()) {
+                Some(value) => dbg.machine_mut().stack.push(value),
+                None => println!("usage: push <int>"),
+            },
+            Some("pop") => _ = dbg.machine_mut().stack.pop(),
+            Some("quit" | "q") => break,
+            Some(other) => println!("unknown command: {}", other),
+            None => {}
+        }
+        if dbg.machine().terminated {
+            println!("program terminated.");
+            break;
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match cli::parse(&args) {
+        Ok(Cmd::Run(args)) => run(args),
+        Ok(Cmd::Obfuscate(args)) => obfuscate(args),
+        Ok(Cmd::Compile(args)) => compile(args),
+        Ok(Cmd::Disasm(args)) => disasm(args),
+        Ok(Cmd::Debug(args)) => debug(args),
+        Err(e) => {
+            eprintln!("error: {}\n", e);
+            eprint!("{}", cli::HELP);
+            std::process::exit(1);
+        }
     }
-    println!("\nProgram terminated with tape state:\n\n{:?}", machine.tape)
 }
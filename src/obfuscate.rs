@@ -0,0 +1,391 @@
+// A configurable multi-pass obfuscation pipeline. Each `Pass` is a named,
+// independent transform over a `Program`; `run` threads a single seeded RNG
+// through all of them so a given (program, passes, seed) triple always
+// produces the same output. `render` turns the final `Program` back into
+// the randomly-whitespaced text `generate_fuxxor` used to emit.
+
+use crate::{Command, Data, Program, PushArgument};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+/// One of the neutral (net-stack-zero) instruction sequences used to pad a
+/// program with dead code. Anything generated here must be a true no-op.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum DeadCodeKind {
+    PushDel,
+    AddDel,
+    SubDel,
+    DupAddDel,
+    DivMultDel,
+    SquareMultDel,
+}
+
+fn dead_code_commands(kind: DeadCodeKind, rng: &mut StdRng) -> Vec<Command> {
+    use Command::*;
+    use PushArgument::Const;
+    match kind {
+        DeadCodeKind::PushDel => vec![Push(Const(rng.gen_range(-3..10))), Del],
+        DeadCodeKind::AddDel => vec![
+            Push(Const(rng.gen_range(-3..10))),
+            Push(Const(rng.gen_range(-3..10))),
+            Add,
+            Del,
+        ],
+        DeadCodeKind::SubDel => vec![
+            Push(Const(rng.gen_range(-3..10))),
+            Push(Const(rng.gen_range(-3..10))),
+            Sub,
+            Del,
+        ],
+        DeadCodeKind::DupAddDel => vec![Push(Const(rng.gen_range(-3..10))), Dup, Add, Del],
+        DeadCodeKind::DivMultDel => vec![
+            Push(Const(rng.gen_range(3..10))),
+            Push(Const(rng.gen_range(3..10))),
+            Div,
+            Push(Const(rng.gen_range(3..10))),
+            Mult,
+            Del,
+        ],
+        DeadCodeKind::SquareMultDel => vec![
+            Push(Const(rng.gen_range(-3..10))),
+            Dup,
+            Mult,
+            Push(Const(rng.gen_range(3..10))),
+            Mult,
+            Del,
+        ],
+    }
+}
+
+/// A named, independent transform over a `Program`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pass {
+    /// Drop existing blank/comment lines so later passes start from a dense program.
+    StripBlankLines,
+    /// Splice `quantity` blank lines in at random positions.
+    JunkWhitespace(usize),
+    /// Splice `quantity` copies of a neutral instruction sequence in at random positions.
+    DeadCode(DeadCodeKind, usize),
+    /// Shuffle the order of basic blocks, rewriting fall-through edges as
+    /// explicit `push <label>; jmp` so physical layout no longer reveals
+    /// control flow.
+    FlattenControlFlow,
+    /// Splice `quantity` always-false guarded dead blocks in at random
+    /// positions: `push x; dup; mult; push <non-square>; eq; push <skip>;
+    /// jmpc` followed by a neutral dead-code sequence and the `skip` label.
+    OpaquePredicates(usize),
+    /// Rebuild the label table from the `Command::Label` lines present,
+    /// resolve every `Push(Label)` to the matching line index, and erase
+    /// the now-redundant label lines. Must run last: every other pass is
+    /// free to leave symbolic labels behind for this to settle.
+    ResolveLabels,
+}
+
+/// The pipeline `generate_fuxxor` used to run, expressed as named passes.
+/// `rounds` repeats the churn (dead code, flattening, opaque predicates)
+/// that many times before a single final label resolution; `StripBlankLines`
+/// and `ResolveLabels` only ever run once each, since repeating them would
+/// shift already-resolved jump targets out from under themselves.
+pub fn default_pipeline(rounds: usize) -> Vec<Pass> {
+    use DeadCodeKind::*;
+    let mut passes = vec![Pass::StripBlankLines];
+    for _ in 0..rounds {
+        passes.extend([
+            Pass::JunkWhitespace(250),
+            Pass::DeadCode(PushDel, 200),
+            Pass::DeadCode(AddDel, 200),
+            Pass::DeadCode(SubDel, 200),
+            Pass::DeadCode(DupAddDel, 200),
+            Pass::DeadCode(DivMultDel, 20),
+            Pass::DeadCode(SquareMultDel, 20),
+            Pass::FlattenControlFlow,
+            Pass::OpaquePredicates(20),
+        ]);
+    }
+    passes.push(Pass::ResolveLabels);
+    passes
+}
+
+/// Runs `program` through `passes` in order, seeding a single RNG from
+/// `seed` so the whole pipeline is reproducible.
+pub fn run(program: Program, passes: &[Pass], seed: u64) -> Program {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut program = program;
+    for pass in passes {
+        program = apply(program, pass, &mut rng);
+    }
+    program
+}
+
+fn apply(program: Program, pass: &Pass, rng: &mut StdRng) -> Program {
+    match pass {
+        Pass::StripBlankLines => strip_blank_lines(program),
+        Pass::JunkWhitespace(quantity) => junk_whitespace(program, *quantity, rng),
+        Pass::DeadCode(kind, quantity) => splice_dead_code(program, *kind, *quantity, rng),
+        Pass::FlattenControlFlow => flatten_control_flow(program, rng),
+        Pass::OpaquePredicates(quantity) => opaque_predicates(program, *quantity, rng),
+        Pass::ResolveLabels => resolve_labels(program),
+    }
+}
+
+fn strip_blank_lines(mut program: Program) -> Program {
+    program.lines.retain(|command| *command != Command::Null);
+    program
+}
+
+fn junk_whitespace(mut program: Program, quantity: usize, rng: &mut StdRng) -> Program {
+    for _ in 0..quantity {
+        let index = rng.gen_range(0..=program.lines.len());
+        program.lines.insert(index, Command::Null);
+    }
+    program
+}
+
+fn splice_dead_code(
+    mut program: Program,
+    kind: DeadCodeKind,
+    quantity: usize,
+    rng: &mut StdRng,
+) -> Program {
+    for _ in 0..quantity {
+        let index = rng.gen_range(0..=program.lines.len());
+        for (offset, command) in dead_code_commands(kind, rng).into_iter().enumerate() {
+            program.lines.insert(index + offset, command);
+        }
+    }
+    program
+}
+
+fn resolve_labels(mut program: Program) -> Program {
+    program.labels = HashMap::new();
+    for (line, command) in program.lines.iter().enumerate() {
+        if let Command::Label(label) = command {
+            program.labels.insert(label.clone(), line);
+        }
+    }
+    for command in program.lines.iter_mut() {
+        if let Command::Push(PushArgument::Label(label)) = command {
+            *command = Command::Push(PushArgument::Const(
+                *program.labels.get(label).unwrap() as Data
+            ));
+        }
+    }
+    program.lines = program
+        .lines
+        .into_iter()
+        .map(|command| match command {
+            Command::Label(_) => Command::Null,
+            other => other,
+        })
+        .collect();
+    program
+}
+
+/// Splits `lines` into basic blocks: a new block starts at a `Label` line
+/// (unless the current block is still empty) and after a `Jmp`/`JmpC`.
+fn split_blocks(lines: Vec<Command>) -> Vec<Vec<Command>> {
+    let mut blocks = Vec::new();
+    let mut current = Vec::new();
+    for command in lines {
+        if matches!(command, Command::Label(_)) && !current.is_empty() {
+            blocks.push(std::mem::take(&mut current));
+        }
+        let ends_block = matches!(command, Command::Jmp | Command::JmpC);
+        current.push(command);
+        if ends_block {
+            blocks.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+    blocks
+}
+
+fn flatten_control_flow(program: Program, rng: &mut StdRng) -> Program {
+    let mut blocks = split_blocks(program.lines);
+    if blocks.len() < 2 {
+        return Program { lines: blocks.into_iter().flatten().collect(), labels: program.labels };
+    }
+
+    // Give every block a label so it can be addressed after shuffling,
+    // regardless of whether it already started with one. The suffix comes
+    // from the pipeline's own RNG (rather than the block index) so repeated
+    // `FlattenControlFlow` rounds never mint the same name twice.
+    let mut entry_labels = Vec::with_capacity(blocks.len());
+    for block in blocks.iter_mut() {
+        let label = match block.first() {
+            Some(Command::Label(name)) => name.clone(),
+            _ => {
+                let synthetic = format!("__flat_block_{:x}", rng.gen::<u64>());
+                block.insert(0, Command::Label(synthetic.clone()));
+                synthetic
+            }
+        };
+        entry_labels.push(label);
+    }
+
+    // Rewrite every fall-through edge, including the one off the end of the
+    // original last block, as an explicit jump. Physical adjacency is about
+    // to be scrambled by the shuffle below, so no block can be allowed to
+    // rely on whatever happens to land after it.
+    // `JmpC` only transfers control on its condition; when that condition is
+    // false at runtime it falls through to the next line just like any other
+    // command, so only an unconditional `Jmp` can be trusted to never do so.
+    let end_label = format!("__flat_end_{:x}", rng.gen::<u64>());
+    let last = blocks.len() - 1;
+    for (i, block) in blocks.iter_mut().enumerate() {
+        let falls_through = !matches!(block.last(), Some(Command::Jmp));
+        if falls_through {
+            let successor = if i == last {
+                end_label.clone()
+            } else {
+                entry_labels[i + 1].clone()
+            };
+            block.push(Command::Push(PushArgument::Label(successor)));
+            block.push(Command::Jmp);
+        }
+    }
+
+    // The entry block must stay first; shuffle the rest.
+    let mut rest: Vec<Vec<Command>> = blocks.drain(1..).collect();
+    rest.shuffle(rng);
+    blocks.extend(rest);
+    blocks.push(vec![Command::Label(end_label)]);
+
+    Program {
+        lines: blocks.into_iter().flatten().collect(),
+        labels: program.labels,
+    }
+}
+
+fn opaque_predicates(mut program: Program, quantity: usize, rng: &mut StdRng) -> Program {
+    for _ in 0..quantity {
+        // Suffixed with a random value rather than the loop index so labels
+        // stay unique even when this pass runs more than once in a pipeline.
+        let skip_label = format!("__opaque_skip_{:x}", rng.gen::<u64>());
+        let x = rng.gen_range(-10..10);
+        let mut chunk = vec![
+            Command::Push(PushArgument::Const(x)),
+            Command::Dup,
+            Command::Mult,
+            // Squares are never negative, so this comparison is always false.
+            Command::Push(PushArgument::Const(-1)),
+            Command::Eq,
+            Command::Push(PushArgument::Label(skip_label.clone())),
+            Command::JmpC,
+        ];
+        chunk.extend(dead_code_commands(DeadCodeKind::PushDel, rng));
+        chunk.push(Command::Label(skip_label));
+
+        let index = rng.gen_range(0..=program.lines.len());
+        for (offset, command) in chunk.into_iter().enumerate() {
+            program.lines.insert(index + offset, command);
+        }
+    }
+    program
+}
+
+/// Renders `program` back to mexirun source text, with the same random
+/// trailing-space and indentation jitter `generate_fuxxor` used to emit.
+pub fn render(program: &Program, rng: &mut StdRng) -> String {
+    let mut indent = String::new();
+    program
+        .lines
+        .iter()
+        .map(|command| {
+            let mut line = format!("{}{}", indent, command.to_string());
+            if rng.gen_ratio(1, 50) {
+                line.push(' ');
+            }
+            if rng.gen_ratio(1, 20) {
+                indent.push(' ');
+            }
+            if rng.gen_ratio(1, 20) {
+                indent.push('\t');
+            }
+            if rng.gen_ratio(1, 8) {
+                indent.pop();
+            }
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+/// Runs `program` through `passes` and renders the result to source text,
+/// both driven off `seed` so the whole thing is reproducible.
+pub fn obfuscate(program: Program, passes: &[Pass], seed: u64) -> String {
+    let program = run(program, passes, seed);
+    let mut render_rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+    render(&program, &mut render_rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{execute_command, parse_program, MachineState};
+
+    fn run_to_completion(program: &Program) -> MachineState {
+        let mut machine = MachineState::default();
+        while !machine.terminated {
+            machine = execute_command(program, machine).expect("program should not fault");
+        }
+        machine
+    }
+
+    #[test]
+    fn default_pipeline_preserves_observable_behavior() {
+        let source = "\
+push 2
+push 3
+add
+push skip
+jmp
+push 999
+skip:
+pusht
+left
+pop
+";
+        let program = parse_program(source);
+        let baseline = run_to_completion(&program);
+
+        let obfuscated = run(program.clone(), &default_pipeline(1), 42);
+        let obfuscated_result = run_to_completion(&obfuscated);
+
+        assert_eq!(baseline.tape.snapshot(), obfuscated_result.tape.snapshot());
+        assert_eq!(baseline.stack, obfuscated_result.stack);
+    }
+
+    #[test]
+    fn dead_code_is_stack_neutral() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for kind in [
+            DeadCodeKind::PushDel,
+            DeadCodeKind::AddDel,
+            DeadCodeKind::SubDel,
+            DeadCodeKind::DupAddDel,
+            DeadCodeKind::DivMultDel,
+            DeadCodeKind::SquareMultDel,
+        ] {
+            let program = Program {
+                lines: dead_code_commands(kind, &mut rng),
+                labels: HashMap::new(),
+            };
+            let machine = run_to_completion(&program);
+            assert!(machine.stack.is_empty(), "{:?} left a dirty stack", kind);
+        }
+    }
+
+    #[test]
+    fn same_seed_is_reproducible() {
+        let program = parse_program("push 1\nprint\n");
+        let a = run(program.clone(), &default_pipeline(1), 1);
+        let b = run(program, &default_pipeline(1), 1);
+        assert_eq!(a.lines, b.lines);
+    }
+}
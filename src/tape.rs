@@ -0,0 +1,93 @@
+// A sparse, bidirectionally-addressable tape. The head is a signed
+// coordinate so `left` past the origin is legal; cells are only materialized
+// in `cells` once written, and reading an untouched cell yields the type's
+// default value.
+
+use crate::Data;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct Tape {
+    head: i64,
+    cells: HashMap<i64, Data>,
+}
+
+impl Tape {
+    pub fn head(&self) -> i64 {
+        self.head
+    }
+
+    pub fn left(&mut self) {
+        self.head -= 1;
+    }
+
+    pub fn right(&mut self) {
+        self.head += 1;
+    }
+
+    pub fn read(&self) -> Data {
+        self.cells.get(&self.head).copied().unwrap_or_default()
+    }
+
+    pub fn write(&mut self, value: Data) {
+        self.cells.insert(self.head, value);
+    }
+
+    /// Number of cells ever written to.
+    pub fn touched(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// A contiguous view over `min..=max` of the touched cell range, with
+    /// untouched gaps filled in as `Data::default()`. Empty if no cell has
+    /// ever been written.
+    pub fn snapshot(&self) -> Vec<Data> {
+        let Some(min) = self.cells.keys().min().copied() else {
+            return Vec::new();
+        };
+        let max = self.cells.keys().max().copied().unwrap();
+        (min..=max)
+            .map(|i| self.cells.get(&i).copied().unwrap_or_default())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_default_for_untouched_cells() {
+        let tape = Tape::default();
+        assert_eq!(tape.read(), Data::default());
+    }
+
+    #[test]
+    fn left_past_origin_is_legal() {
+        let mut tape = Tape::default();
+        tape.left();
+        tape.left();
+        tape.write(7);
+        assert_eq!(tape.head(), -2);
+        assert_eq!(tape.read(), 7);
+    }
+
+    #[test]
+    fn snapshot_spans_touched_range_with_gaps_defaulted() {
+        let mut tape = Tape::default();
+        tape.write(1);
+        tape.right();
+        tape.right();
+        tape.write(3);
+        tape.left();
+        tape.left();
+        tape.left();
+        tape.write(-1);
+        assert_eq!(tape.snapshot(), vec![-1, 1, 0, 3]);
+    }
+
+    #[test]
+    fn snapshot_is_empty_when_nothing_was_written() {
+        assert_eq!(Tape::default().snapshot(), Vec::<Data>::new());
+    }
+}